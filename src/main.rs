@@ -1,6 +1,7 @@
 use anyhow::{bail, Context, Result};
 use arboard::{Clipboard, ImageData};
-use chrono::{Datelike};
+use chrono::{Datelike, NaiveDateTime};
+use sha2::{Digest, Sha256};
 use image::{ImageFormat, RgbaImage};
 use std::path::{Path, PathBuf};
 use clap::{Parser, Subcommand, ValueEnum};
@@ -21,59 +22,164 @@ enum Commands {
         /// working directory
         #[arg(long, env = "PASTER_WORK_DIR")]
         cd: Option<PathBuf>,
+        /// target image format for pasted images; omit to copy image files
+        /// verbatim (raw clipboard images are then saved as png)
+        #[arg(long, value_enum)]
+        format: Option<ImageFormatArg>,
+        /// lossy quality 0..=100 for jpeg (default 80) and webp (omit or use a
+        /// negative value for lossless webp); ignored for png
+        #[arg(long)]
+        quality: Option<i32>,
+        /// delete the source files after copying them from a file list
+        #[arg(long)]
+        remove_original: bool,
+        /// skip writing an image whose content matches a file recorded in
+        /// dest_dir's .paster-index by an earlier --dedup paste
+        #[arg(long)]
+        dedup: bool,
+        /// filename template, supporting {stem} {ext} {timestamp} {seq} and
+        /// {date:%Y-%m-%d}; slashes create dated subdirectories under dest_dir
+        #[arg(long, default_value = "{stem}_{timestamp}.{ext}")]
+        name_template: String,
     },
     /// print a date
     Date {
-        /// when
-        #[arg(value_parser)]
-        when: WhatTypes,
+        /// when: a keyword (yesterday, today, tomorrow, next-week), an explicit
+        /// date, or a `from|to` inclusive range
+        when: String,
         /// format
         #[arg(short, long, env = "PASTER_DATE_FORMAT", default_value = "%d/%m/%y")]
         format: String,
+        /// render the date as a human-friendly delta from now (e.g. "in 1 Week")
+        #[arg(long)]
+        relative: bool,
     },
 }
 
-#[derive(Clone, ValueEnum)]
+#[derive(Clone, Copy, ValueEnum)]
 #[value(rename_all = "kebab-case")]
-enum WhatTypes {
-    Yesterday,
-    Today,
-    Tomorrow,
-    NextWeek,
+enum ImageFormatArg {
+    Png,
+    Jpeg,
+    Webp,
+}
+
+impl ImageFormatArg {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormatArg::Png => "png",
+            ImageFormatArg::Jpeg => "jpg",
+            ImageFormatArg::Webp => "webp",
+        }
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Paste { dest_dir, cd } => paste(dest_dir, cd)?,
-        Commands::Date {   when, format } => date(when, &format)?,
+        Commands::Paste {
+            dest_dir,
+            cd,
+            format,
+            quality,
+            remove_original,
+            dedup,
+            name_template,
+        } => paste(dest_dir, cd, format, quality, remove_original, dedup, &name_template)?,
+        Commands::Date { when, format, relative } => date(&when, &format, relative)?,
     }
     
     Ok(())
 }
 
-fn date(when: WhatTypes, format: &str) -> Result<()> {
-    match when {
-        WhatTypes::Yesterday => {
-            let yesterday = chrono::Utc::now() - chrono::Duration::days(1);
-            println!("{}", yesterday.format(format));
-        }
-        WhatTypes::Today => {
-            println!("{}", chrono::Utc::now().format(format));
-        }
-        WhatTypes::Tomorrow => {
-            println!("{}", (chrono::Utc::now() + chrono::Duration::days(1)).format(format));
-        }
-        WhatTypes::NextWeek => {
+fn keyword_date(when: &str) -> Option<NaiveDateTime> {
+    let dt = match when {
+        "yesterday" => chrono::Utc::now().naive_utc() - chrono::Duration::days(1),
+        "today" => chrono::Utc::now().naive_utc(),
+        "tomorrow" => chrono::Utc::now().naive_utc() + chrono::Duration::days(1),
+        "next-week" => {
             // get next week monday
             let today = chrono::Local::now().date_naive();
             let days_since_monday = today.weekday().num_days_from_monday() as i64;
             let next_monday = today + chrono::Duration::days(7 - days_since_monday);
+            next_monday.and_hms_opt(0, 0, 0)?
+        }
+        _ => return None,
+    };
+    Some(dt)
+}
+
+fn parse_date(when: &str) -> Result<NaiveDateTime> {
+    let when = when.trim();
+    if let Ok(dt) = NaiveDateTime::parse_from_str(when, "%Y-%m-%dT%H:%M:%S") {
+        return Ok(dt);
+    }
+    // fallback for a bare `YYYY-MM-DD` with no time component
+    NaiveDateTime::parse_from_str(&format!("{when}T00:00:00"), "%Y-%m-%dT%H:%M:%S")
+        .with_context(|| format!("can't parse date '{when}'"))
+}
+
+/// Render a duration as the largest non-zero unit with the right pluralization,
+/// e.g. `in 1 Week`, `in 2 Days`, `3 Weeks ago`.
+fn humanize(delta: chrono::Duration) -> String {
+    let future = delta.num_seconds() >= 0;
+    let d = if future { delta } else { -delta };
+
+    let (n, unit) = if d.num_weeks() / 52 > 0 {
+        (d.num_weeks() / 52, "Year")
+    } else if d.num_weeks() > 0 {
+        (d.num_weeks(), "Week")
+    } else if d.num_days() > 0 {
+        (d.num_days(), "Day")
+    } else if d.num_hours() > 0 {
+        (d.num_hours(), "Hour")
+    } else if d.num_minutes() > 0 {
+        (d.num_minutes(), "Minute")
+    } else {
+        (d.num_seconds(), "Second")
+    };
+
+    let unit = if n == 1 { unit.to_string() } else { format!("{unit}s") };
+    if future {
+        format!("in {n} {unit}")
+    } else {
+        format!("{n} {unit} ago")
+    }
+}
+
+fn date(when: &str, format: &str, relative: bool) -> Result<()> {
+    let dates = if let Some(dt) = keyword_date(when) {
+        vec![dt]
+    } else {
+        match when.split_once('|') {
+            Some((from, to)) => {
+                let (from, to) = (parse_date(from)?, parse_date(to)?);
+                if from > to {
+                    bail!("range start '{from}' is after its end '{to}'");
+                }
+                let mut out = Vec::new();
+                let mut day = from.date();
+                let end = to.date();
+                while day <= end {
+                    out.push(day.and_hms_opt(0, 0, 0).unwrap());
+                    day += chrono::Duration::days(1);
+                }
+                out
+            }
+            None => vec![parse_date(when)?],
+        }
+    };
 
-            println!("{}", next_monday.format(format));
+    let now = chrono::Utc::now().naive_utc();
+    for dt in dates {
+        if relative {
+            println!("{}", humanize(dt - now));
+        } else {
+            println!("{}", dt.format(format));
         }
     }
+
     Ok(())
 }
 
@@ -81,6 +187,129 @@ fn timestamp() -> String {
     chrono::Utc::now().format("%Y%m%d_%H%M%S_%3f").to_string()
 }
 
+fn encode_image(img: &RgbaImage, format: ImageFormatArg, quality: Option<i32>) -> Result<Vec<u8>> {
+    let (width, height) = (img.width(), img.height());
+    match format {
+        ImageFormatArg::Png => {
+            let mut buf = std::io::Cursor::new(Vec::new());
+            img.write_to(&mut buf, ImageFormat::Png)
+                .with_context(|| "can't encode PNG")?;
+            Ok(buf.into_inner())
+        }
+        ImageFormatArg::Jpeg => {
+            let rgb = image::DynamicImage::ImageRgba8(img.clone()).to_rgb8();
+            let mut buf = std::io::Cursor::new(Vec::new());
+            let q = quality.unwrap_or(80).clamp(0, 100) as u8;
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buf, q)
+                .encode_image(&rgb)
+                .with_context(|| "can't encode JPEG")?;
+            Ok(buf.into_inner())
+        }
+        ImageFormatArg::Webp => {
+            let encoder = webp::Encoder::from_rgba(img.as_raw(), width, height);
+            let encoded = match quality {
+                Some(q) if q >= 0 => encoder.encode(q.min(100) as f32),
+                _ => encoder.encode_lossless(),
+            };
+            Ok(encoded.to_vec())
+        }
+    }
+}
+
+/// name of the sidecar index mapping content digest -> stored filename
+const INDEX_FILE: &str = ".paster-index";
+
+fn content_hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Look up an already-stored file with the given content hash in `dest_dir`.
+fn dedup_lookup(dest_dir: &Path, hash: &str) -> Result<Option<String>> {
+    let index = dest_dir.join(INDEX_FILE);
+    if !index.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&index)?;
+    for line in content.lines() {
+        if let Some((h, name)) = line.split_once(' ') {
+            if h == hash && dest_dir.join(name).exists() {
+                return Ok(Some(name.to_string()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Record a newly stored file's content hash in the sidecar index.
+fn dedup_record(dest_dir: &Path, hash: &str, filename: &str) -> Result<()> {
+    use std::io::Write;
+    let index = dest_dir.join(INDEX_FILE);
+    let mut f = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&index)?;
+    writeln!(f, "{hash} {filename}")?;
+    Ok(())
+}
+
+/// Render a filename template, expanding `{stem}`, `{ext}`, `{timestamp}`,
+/// `{seq}` and `{date:FMT}` tokens. The result may contain path separators
+/// (e.g. from `{date:%Y/%m}`) to place the file in a dated subdirectory.
+fn render_name(template: &str, stem: &str, ext: &str, seq: usize) -> String {
+    let now = chrono::Local::now();
+    let ts = timestamp();
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(idx) = rest.find('{') {
+        out.push_str(&rest[..idx]);
+        let after = &rest[idx..];
+        let Some(end) = after.find('}') else {
+            out.push_str(after);
+            return out;
+        };
+        let token = &after[1..end];
+        let rendered = match token {
+            "stem" => stem.to_string(),
+            "ext" => ext.to_string(),
+            "timestamp" => ts.clone(),
+            "seq" => seq.to_string(),
+            t if t.starts_with("date:") => now.format(&t["date:".len()..]).to_string(),
+            other => format!("{{{other}}}"),
+        };
+        out.push_str(&rendered);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// If `path` already exists, append an incrementing suffix to the stem until a
+/// free name is found, so pasted files never silently overwrite each other.
+fn collision_safe(path: PathBuf) -> PathBuf {
+    if !path.exists() {
+        return path;
+    }
+    let parent = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let ext = path.extension().map(|e| e.to_string_lossy().to_string());
+    let mut i = 1;
+    loop {
+        let name = match &ext {
+            Some(ext) => format!("{stem}_{i}.{ext}"),
+            None => format!("{stem}_{i}"),
+        };
+        let candidate = parent.join(name);
+        if !candidate.exists() {
+            return candidate;
+        }
+        i += 1;
+    }
+}
+
 fn is_image_file<P: AsRef<Path>>(file_path: P) -> bool {
     if let Some(extension) = file_path.as_ref().extension() {
         if let Some(ext_str) = extension.to_str() {
@@ -95,31 +324,93 @@ fn is_image_file<P: AsRef<Path>>(file_path: P) -> bool {
     false
 }
 
-fn handle_file_list(file_list: Vec<PathBuf>, dest_dir: impl AsRef<Path>) -> Result<()> {
-    for file in file_list.iter() {
-        let emark = if is_image_file(&file) { "!" } else { "" };
+fn handle_file_list(
+    file_list: Vec<PathBuf>,
+    dest_dir: impl AsRef<Path>,
+    format: Option<ImageFormatArg>,
+    quality: Option<i32>,
+    remove_original: bool,
+    dedup: bool,
+    name_template: &str,
+) -> Result<()> {
+    for (seq, file) in file_list.iter().enumerate() {
+        let is_image = is_image_file(&file);
+        let emark = if is_image { "!" } else { "" };
         let filename = file
             .file_stem()
             .with_context(|| "Could not determine filename")?
             .to_string_lossy()
             .replace(" ", "_");
-        let extension = file
-            .extension()
-            .with_context(|| "Could not determine extension")?
-            .to_string_lossy();
-        let new_filename = format!("{}_{}.{}", filename, timestamp(), extension);
-        std::fs::create_dir_all(&dest_dir)?;
-        let dest_path = dest_dir.as_ref().join(&new_filename);
 
-        std::fs::copy(file, &dest_path).with_context(|| "can't copy file")?;
+        // re-encode only when the user explicitly asked for an image format;
+        // otherwise copy the file verbatim, preserving its original bytes.
+        let reencode = is_image.then_some(format).flatten();
 
+        let (bytes, extension) = match reencode {
+            Some(fmt) => {
+                let img = image::open(file).with_context(|| "can't open image")?.to_rgba8();
+                (encode_image(&img, fmt, quality)?, fmt.extension().to_string())
+            }
+            None => {
+                let extension = file
+                    .extension()
+                    .with_context(|| "Could not determine extension")?
+                    .to_string_lossy()
+                    .into_owned();
+                (std::fs::read(file).with_context(|| "can't read file")?, extension)
+            }
+        };
+
+        let hash = (dedup && is_image).then(|| content_hash(&bytes));
+
+        if let Some(hash) = &hash {
+            if let Some(existing) = dedup_lookup(dest_dir.as_ref(), hash)? {
+                let dest_path = dest_dir.as_ref().join(&existing);
+                if remove_original {
+                    std::fs::remove_file(file).with_context(|| "can't remove original")?;
+                }
+                println!("{emark}[{filename}]({})", dest_path.to_string_lossy());
+                continue;
+            }
+        }
+
+        let rel = render_name(name_template, &filename, &extension, seq);
+        let dest_path = prepare_dest(dest_dir.as_ref(), &rel)?;
+        std::fs::write(&dest_path, &bytes).with_context(|| "can't write file")?;
+        if let Some(hash) = &hash {
+            if let Ok(name) = dest_path.strip_prefix(dest_dir.as_ref()) {
+                dedup_record(dest_dir.as_ref(), hash, &name.to_string_lossy())?;
+            }
+        }
+        if remove_original {
+            std::fs::remove_file(file).with_context(|| "can't remove original")?;
+        }
         println!("{emark}[{filename}]({})", dest_path.to_string_lossy());
     }
 
     Ok(())
 }
 
-fn handle_image_data(image_data: ImageData, dest_dir: impl AsRef<Path>) -> Result<()> {
+/// Resolve a rendered relative name against `dest_dir`, creating any parent
+/// directories and picking a collision-free path.
+fn prepare_dest(dest_dir: &Path, rel: &str) -> Result<PathBuf> {
+    let dest_path = collision_safe(dest_dir.join(rel));
+    if let Some(parent) = dest_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    Ok(dest_path)
+}
+
+fn handle_image_data(
+    image_data: ImageData,
+    dest_dir: impl AsRef<Path>,
+    format: Option<ImageFormatArg>,
+    quality: Option<i32>,
+    dedup: bool,
+    name_template: &str,
+) -> Result<()> {
+    // raw clipboard images have no source file, so default to PNG
+    let format = format.unwrap_or(ImageFormatArg::Png);
     let width = image_data.width as u32;
     let height = image_data.height as u32;
 
@@ -136,11 +427,27 @@ fn handle_image_data(image_data: ImageData, dest_dir: impl AsRef<Path>) -> Resul
         }
     };
 
-    let new_filename = format!("img_{}.png", timestamp());
-    std::fs::create_dir_all(&dest_dir)?;
-    let dest_path = dest_dir.as_ref().join(&new_filename);
+    let bytes = encode_image(&img, format, quality)?;
+
+    let hash = dedup.then(|| content_hash(&bytes));
 
-    img.save_with_format(&dest_path, ImageFormat::Png)?;
+    if let Some(hash) = &hash {
+        if let Some(existing) = dedup_lookup(dest_dir.as_ref(), hash)? {
+            let dest_path = dest_dir.as_ref().join(&existing);
+            println!("![]({})", dest_path.to_string_lossy());
+            return Ok(());
+        }
+    }
+
+    let rel = render_name(name_template, "img", format.extension(), 0);
+    let dest_path = prepare_dest(dest_dir.as_ref(), &rel)?;
+
+    std::fs::write(&dest_path, &bytes).with_context(|| "can't write image")?;
+    if let Some(hash) = &hash {
+        if let Ok(name) = dest_path.strip_prefix(dest_dir.as_ref()) {
+            dedup_record(dest_dir.as_ref(), hash, &name.to_string_lossy())?;
+        }
+    }
 
     println!("![]({})", dest_path.to_string_lossy());
 
@@ -153,7 +460,15 @@ fn handle_text(content: String) {
     println!("```");
 }
 
-fn paste(dest_dir: impl AsRef<Path>, work_dir: Option<impl AsRef<Path>>) -> Result<()> {
+fn paste(
+    dest_dir: impl AsRef<Path>,
+    work_dir: Option<impl AsRef<Path>>,
+    format: Option<ImageFormatArg>,
+    quality: Option<i32>,
+    remove_original: bool,
+    dedup: bool,
+    name_template: &str,
+) -> Result<()> {
     if let Some(work_dir) = work_dir {
         std::env::set_current_dir(&work_dir)?;
     }
@@ -161,12 +476,12 @@ fn paste(dest_dir: impl AsRef<Path>, work_dir: Option<impl AsRef<Path>>) -> Resu
     let mut ctx = Clipboard::new()?;
 
     if let Ok(file_list) = ctx.get().file_list() {
-        handle_file_list(file_list, dest_dir)?;
+        handle_file_list(file_list, dest_dir, format, quality, remove_original, dedup, name_template)?;
         return Ok(());
     }
 
     if let Ok(image) = ctx.get_image() {
-        handle_image_data(image, dest_dir)?;
+        handle_image_data(image, dest_dir, format, quality, dedup, name_template)?;
         return Ok(());
     }
 
@@ -178,4 +493,66 @@ fn paste(dest_dir: impl AsRef<Path>, work_dir: Option<impl AsRef<Path>>) -> Resu
     handle_text(content);
 
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn humanize_picks_largest_unit_with_pluralization() {
+        assert_eq!(humanize(Duration::days(2)), "in 2 Days");
+        assert_eq!(humanize(Duration::hours(1)), "in 1 Hour");
+        assert_eq!(humanize(Duration::weeks(3)), "in 3 Weeks");
+        assert_eq!(humanize(Duration::seconds(1)), "in 1 Second");
+    }
+
+    #[test]
+    fn humanize_rolls_weeks_into_years() {
+        assert_eq!(humanize(Duration::weeks(52)), "in 1 Year");
+        assert_eq!(humanize(Duration::weeks(104)), "in 2 Years");
+    }
+
+    #[test]
+    fn humanize_marks_the_past() {
+        assert_eq!(humanize(Duration::days(-2)), "2 Days ago");
+    }
+
+    #[test]
+    fn parse_date_accepts_bare_and_full_dates() {
+        let bare = parse_date("2024-03-01").unwrap();
+        assert_eq!(bare, parse_date("2024-03-01T00:00:00").unwrap());
+        assert_eq!(bare.format("%Y-%m-%d %H:%M").to_string(), "2024-03-01 00:00");
+
+        let full = parse_date("2024-03-01T13:30:00").unwrap();
+        assert_eq!(full.format("%H:%M").to_string(), "13:30");
+    }
+
+    #[test]
+    fn parse_date_rejects_garbage() {
+        assert!(parse_date("not-a-date").is_err());
+    }
+
+    #[test]
+    fn render_name_expands_known_tokens() {
+        assert_eq!(render_name("{stem}.{ext}", "shot", "png", 0), "shot.png");
+        assert_eq!(render_name("{seq}", "shot", "png", 5), "5");
+
+        let rendered = render_name("{stem}_{timestamp}.{ext}", "img", "png", 0);
+        assert!(rendered.starts_with("img_"));
+        assert!(rendered.ends_with(".png"));
+    }
+
+    #[test]
+    fn render_name_preserves_unknown_tokens() {
+        assert_eq!(render_name("{bogus}", "shot", "png", 0), "{bogus}");
+    }
+
+    #[test]
+    fn render_name_expands_date_token() {
+        let rendered = render_name("{date:%Y}", "shot", "png", 0);
+        assert_eq!(rendered.len(), 4);
+        assert!(rendered.chars().all(|c| c.is_ascii_digit()));
+    }
 }
\ No newline at end of file